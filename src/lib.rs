@@ -3,18 +3,16 @@
 #![allow(mut_from_ref)]
 
 //! Thread-local clonable arena allocator
-extern crate either;
 extern crate vec_map;
 
 use std::cell::{
     BorrowError, BorrowMutError, Ref, RefCell, RefMut, UnsafeCell,
 };
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Index, IndexMut};
 use std::rc::Rc;
 use std::{fmt, mem};
 
-use either::Either;
 use vec_map::VecMap;
 
 const BASE: usize = 32;
@@ -30,13 +28,111 @@ thread_local! {
 /// A reference into the arena that can be used for lookup
 /// Also contains a hacky !Send workaround by bundling a
 /// `PhantomData<Rc<_>>`
-#[derive(Clone, Copy, Debug)]
+///
+/// The offset and generation are packed together into a single `u64`
+/// ([`ArenaIdx::OFFSET_BITS`] and [`ArenaIdx::GEN_BITS`] wide
+/// respectively) instead of two separate fields, so the split between
+/// "how many live slots an arena can address" and "how many times a slot
+/// can be removed/reused before its generation counter wraps" is a real,
+/// adjustable trade-off rather than two independently-sized fields.
+#[derive(Clone, Copy)]
 pub struct ArenaIdx {
     arena: usize,
-    offset: usize,
+    packed: u64,
     _marker: PhantomData<Rc<ArenaIdxIsNotSend>>,
 }
 
+impl ArenaIdx {
+    /// Bits of `packed` given to a slot's offset within its arena.
+    /// [`Arena::append`] panics if a slot's offset would overflow this
+    /// many bits. Narrow this (and widen [`ArenaIdx::GEN_BITS`] to match,
+    /// since the two share one `u64`) to trade maximum arena capacity for
+    /// a generation counter that takes longer to wrap.
+    pub const OFFSET_BITS: u32 = 48;
+
+    /// Bits of `packed` given to a slot's generation counter; see
+    /// [`ArenaIdx::OFFSET_BITS`] for the capacity/wrap trade-off the two
+    /// widths share. The split does not change the size of an `ArenaIdx`,
+    /// and generations are stored as `u32`, so a width above 32 bits
+    /// wraps no later than 32 bits would.
+    pub const GEN_BITS: u32 = 64 - Self::OFFSET_BITS;
+
+    const GEN_MASK: u64 = (1u64 << Self::GEN_BITS) - 1;
+    const MAX_OFFSET: u64 = (1u64 << Self::OFFSET_BITS) - 1;
+
+    fn new(arena: usize, offset: usize, gen: u32) -> Self {
+        assert!(
+            offset as u64 <= Self::MAX_OFFSET,
+            "offset exceeds ArenaIdx::OFFSET_BITS"
+        );
+        ArenaIdx {
+            arena,
+            packed: ((offset as u64) << Self::GEN_BITS)
+                | (gen as u64 & Self::GEN_MASK),
+            _marker: PhantomData,
+        }
+    }
+
+    fn offset(&self) -> usize {
+        (self.packed >> Self::GEN_BITS) as usize
+    }
+
+    fn gen(&self) -> u32 {
+        (self.packed & Self::GEN_MASK) as u32
+    }
+}
+
+impl fmt::Debug for ArenaIdx {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArenaIdx")
+            .field("arena", &self.arena)
+            .field("offset", &self.offset())
+            .field("gen", &self.gen())
+            .finish()
+    }
+}
+
+/// Error returned when a value could not be read out of the arena.
+#[derive(Debug)]
+pub enum GetError {
+    /// The slot is already mutably borrowed elsewhere.
+    Borrow(BorrowError),
+    /// The slot has been removed.
+    Removed,
+    /// The index is stale: its generation no longer matches the slot's,
+    /// because the slot was removed and/or reused since the index was
+    /// produced.
+    StaleIndex,
+}
+
+/// Error returned when a value could not be mutably read out of the arena.
+#[derive(Debug)]
+pub enum GetMutError {
+    /// The slot is already borrowed elsewhere.
+    Borrow(BorrowMutError),
+    /// The slot could not be cloned out of an older arena during COW.
+    BorrowImmutable(BorrowError),
+    /// The slot has been removed.
+    Removed,
+    /// The index is stale: its generation no longer matches the slot's,
+    /// because the slot was removed and/or reused since the index was
+    /// produced.
+    StaleIndex,
+}
+
+/// Error returned when a value could not be removed from the arena.
+#[derive(Debug)]
+pub enum RemoveError {
+    /// The slot is already borrowed elsewhere.
+    Borrow(BorrowMutError),
+    /// The slot has already been removed.
+    Removed,
+    /// The index is stale: its generation no longer matches the slot's,
+    /// because the slot was removed and/or reused since the index was
+    /// produced.
+    StaleIndex,
+}
+
 #[derive(Debug)]
 /// An immutable reference into the arena
 pub struct ArenaRef<'a, T: 'a>(Ref<'a, T>);
@@ -65,9 +161,39 @@ impl<'a, T> DerefMut for ArenaRefMut<'a, T> {
     }
 }
 
+impl<'a, T> ArenaRef<'a, T> {
+    /// Projects this reference into a borrow of a subfield or variant,
+    /// keeping the underlying `RefCell` borrow alive.
+    pub fn map<U, F>(self, f: F) -> ArenaRef<'a, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        ArenaRef(Ref::map(self.0, f))
+    }
+}
+
+impl<'a, T> ArenaRefMut<'a, T> {
+    /// Projects this reference into a borrow of a subfield or variant,
+    /// keeping the underlying `RefCell` borrow alive.
+    pub fn map<U, F>(self, f: F) -> ArenaRefMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        ArenaRefMut(RefMut::map(self.0, f))
+    }
+}
+
+/// A single arena slot: its current generation (bumped each time the slot
+/// is vacated), and its value, or `None` if the slot has been removed.
+struct Slot<T> {
+    gen: u32,
+    value: Option<T>,
+}
+
 struct ArenaInner<T> {
-    rows: UnsafeCell<[Vec<RefCell<T>>; NUM_ALLOCATIONS]>,
+    rows: UnsafeCell<[Vec<RefCell<Slot<T>>>; NUM_ALLOCATIONS]>,
     len: RefCell<usize>,
+    free: RefCell<Vec<usize>>,
 }
 
 impl<T> Default for ArenaInner<T> {
@@ -75,6 +201,7 @@ impl<T> Default for ArenaInner<T> {
         ArenaInner {
             rows: UnsafeCell::new(Default::default()),
             len: RefCell::new(0),
+            free: RefCell::new(Vec::new()),
         }
     }
 }
@@ -160,16 +287,17 @@ impl<T: Clone> Arena<T> {
 
     /// Try to get a reference into the arena.
     ///
-    /// Returns an error if value cannot be borrowed
+    /// Returns an error if the value cannot be borrowed, or if it has
+    /// been removed.
     pub fn try_get(
         &self,
         arena_idx: &ArenaIdx,
-    ) -> Result<ArenaRef<T>, BorrowError> {
+    ) -> Result<ArenaRef<T>, GetError> {
         let arenas = unsafe { &mut *self.arenas.get() };
         arenas
             .get(arena_idx.arena)
             .expect("Invalid arena_idx")
-            .try_get(arena_idx.offset)
+            .try_get(arena_idx.offset(), arena_idx.gen())
     }
 
     /// Get a mutable reference into the arena.
@@ -181,23 +309,28 @@ impl<T: Clone> Arena<T> {
 
     /// Try to get a mutable reference into the arena.
     ///
-    /// Returns an error if value cannot be borrowed
+    /// Returns an error if the value cannot be borrowed, or if it has
+    /// been removed.
     pub fn try_get_mut(
         &self,
         arena_idx: &mut ArenaIdx,
-    ) -> Result<ArenaRefMut<T>, Either<BorrowMutError, BorrowError>> {
+    ) -> Result<ArenaRefMut<T>, GetMutError> {
         let arenas = unsafe { &mut *self.arenas.get() };
         let id = *self.id.borrow();
         if arena_idx.arena == id {
             arenas
                 .get(arena_idx.arena)
                 .expect("Invalid arena_idx")
-                .try_get_mut(arena_idx.offset)
-                .map_err(|e| Either::Left(e))
+                .try_get_mut(arena_idx.offset(), arena_idx.gen())
         } else {
-            let t: T =
-                (*self.try_get(arena_idx).map_err(|e| Either::Right(e))?)
-                    .clone();
+            let t: T = (*self
+                .try_get(arena_idx)
+                .map_err(|e| match e {
+                    GetError::Borrow(e) => GetMutError::BorrowImmutable(e),
+                    GetError::Removed => GetMutError::Removed,
+                    GetError::StaleIndex => GetMutError::StaleIndex,
+                })?)
+            .clone();
             *arena_idx = self.append(t);
             self.try_get_mut(arena_idx)
         }
@@ -209,6 +342,153 @@ impl<T: Clone> Arena<T> {
         let id = *self.id.borrow();
         arenas.get(id).expect("Invalid arena_idx").append(id, t)
     }
+
+    /// Appends every item of `items`, reserving row capacity up front
+    /// instead of bumping the length one element at a time.
+    pub fn append_slice(&self, items: &[T]) -> Vec<ArenaIdx> {
+        self.append_iter(items.iter().cloned())
+    }
+
+    /// Appends every item yielded by `iter`, reserving row capacity up
+    /// front instead of bumping the length one element at a time.
+    pub fn append_iter<I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+    ) -> Vec<ArenaIdx> {
+        let arenas = unsafe { &mut *self.arenas.get() };
+        let id = *self.id.borrow();
+        arenas
+            .get(id)
+            .expect("Invalid arena_idx")
+            .append_iter(id, iter)
+    }
+
+    /// Removes a value from the arena, returning it by value.
+    ///
+    /// Returns `None` if the slot was already removed or is currently
+    /// borrowed. Panics on out-of-bound access.
+    pub fn remove(&self, arena_idx: &ArenaIdx) -> Option<T> {
+        self.try_remove(arena_idx).ok()
+    }
+
+    /// Try to remove a value from the arena, returning it by value.
+    ///
+    /// Returns an error if the slot is currently borrowed, or has already
+    /// been removed. Panics on out-of-bound access.
+    pub fn try_remove(
+        &self,
+        arena_idx: &ArenaIdx,
+    ) -> Result<T, RemoveError> {
+        let arenas = unsafe { &mut *self.arenas.get() };
+        arenas
+            .get(arena_idx.arena)
+            .expect("Invalid arena_idx")
+            .try_remove(arena_idx.offset(), arena_idx.gen())
+    }
+
+    /// Consumes the arena, returning its live values in offset order.
+    ///
+    /// Only the values reachable through this arena's *current* id are
+    /// recovered, not the whole COW history shared with other handles
+    /// produced by [`clone`](Clone::clone) or [`merge`](Arena::merge); if
+    /// the current id's storage is still shared with one of those
+    /// handles, the values are cloned out instead of moved.
+    pub fn into_vec(self) -> Vec<T> {
+        let id = *self.id.borrow();
+        let arenas = unsafe { &mut *self.arenas.get() };
+        let inner = arenas.remove(id).expect("Invalid arena_idx");
+        match Rc::try_unwrap(inner) {
+            Ok(inner) => inner.into_vec(),
+            Err(inner) => inner.cloned_vec(),
+        }
+    }
+
+    /// Removes every live value stored under this arena's current id,
+    /// returning them in offset order, and leaves that storage empty.
+    /// As with [`into_vec`](Arena::into_vec), values in the COW history
+    /// shared with other handles are left in place.
+    ///
+    /// Unlike [`into_vec`](Arena::into_vec), the arena itself stays
+    /// alive afterwards: further `append` calls reuse the freed slots.
+    pub fn drain(&self) -> impl Iterator<Item = T> {
+        let arenas = unsafe { &mut *self.arenas.get() };
+        let id = *self.id.borrow();
+        arenas
+            .get(id)
+            .expect("Invalid arena_idx")
+            .drain()
+            .into_iter()
+    }
+
+    /// Iterates over every live entry of the current arena, in offset
+    /// order, yielding the reconstructed `ArenaIdx` alongside each value.
+    ///
+    /// The set of offsets to visit is snapshotted up front, so a slot
+    /// removed after the snapshot but before it's visited (including from
+    /// inside the loop body) is skipped rather than causing a panic. An
+    /// entry that cannot be borrowed when it is reached is skipped too.
+    pub fn iter(&self) -> impl Iterator<Item = (ArenaIdx, ArenaRef<T>)> + '_ {
+        let arenas = unsafe { &mut *self.arenas.get() };
+        let id = *self.id.borrow();
+        let offsets =
+            arenas.get(id).expect("Invalid arena_idx").live_offsets();
+        offsets.into_iter().filter_map(move |(offset, gen)| {
+            let idx = ArenaIdx::new(id, offset, gen);
+            let r = self.try_get(&idx).ok()?;
+            Some((idx, r))
+        })
+    }
+
+    /// Like [`iter`](Arena::iter), but yields mutable references.
+    pub fn iter_mut(
+        &self,
+    ) -> impl Iterator<Item = (ArenaIdx, ArenaRefMut<T>)> + '_ {
+        let arenas = unsafe { &mut *self.arenas.get() };
+        let id = *self.id.borrow();
+        let offsets =
+            arenas.get(id).expect("Invalid arena_idx").live_offsets();
+        offsets.into_iter().filter_map(move |(offset, gen)| {
+            let mut idx = ArenaIdx::new(id, offset, gen);
+            let r = self.try_get_mut(&mut idx).ok()?;
+            Some((idx, r))
+        })
+    }
+}
+
+impl<T: Clone> Index<ArenaIdx> for Arena<T> {
+    type Output = T;
+
+    /// Indexes into the arena, as [`arenatree`](https://docs.rs/arenatree)
+    /// does with its `NodeId`.
+    ///
+    /// Because slots are backed by a `RefCell`, this leaks the underlying
+    /// borrow and never releases it, so the slot can never be mutably
+    /// borrowed again; prefer [`Arena::get`] if the
+    /// borrow needs to be released.
+    fn index(&self, idx: ArenaIdx) -> &T {
+        let r = self.get(&idx);
+        let ptr: *const T = &*r;
+        mem::forget(r);
+        unsafe { &*ptr }
+    }
+}
+
+impl<T: Clone> IndexMut<ArenaIdx> for Arena<T> {
+    /// Leaks the underlying borrow and never releases it, so the slot
+    /// can never be borrowed again; prefer
+    /// [`Arena::get_mut`] if the borrow needs to be released.
+    fn index_mut(&mut self, mut idx: ArenaIdx) -> &mut T {
+        let mut r = self.get_mut(&mut idx);
+        let ptr: *mut T = &mut *r;
+        mem::forget(r);
+        unsafe { &mut *ptr }
+    }
+}
+
+impl<T: Clone> Extend<T> for Arena<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.append_iter(iter);
+    }
 }
 
 impl<T> fmt::Debug for Arena<T>
@@ -221,29 +501,104 @@ where
 }
 
 impl<T> ArenaInner<T> {
-    fn try_get(&self, offset: usize) -> Result<ArenaRef<T>, BorrowError> {
+    fn try_get(&self, offset: usize, gen: u32) -> Result<ArenaRef<T>, GetError> {
         if offset >= *self.len.borrow() {
             panic!("Index out of bounds")
         }
         let (row, col) = Self::index(offset);
         unsafe {
             let rows = self.rows.get();
-            Ok(ArenaRef((*rows)[row][col].try_borrow()?))
+            let slot =
+                (*rows)[row][col].try_borrow().map_err(GetError::Borrow)?;
+            if slot.gen != gen {
+                return Err(GetError::StaleIndex);
+            }
+            if slot.value.is_none() {
+                return Err(GetError::Removed);
+            }
+            Ok(ArenaRef(Ref::map(slot, |slot| slot.value.as_ref().unwrap())))
         }
     }
 
     pub fn try_get_mut(
         &self,
         offset: usize,
-    ) -> Result<ArenaRefMut<T>, BorrowMutError> {
+        gen: u32,
+    ) -> Result<ArenaRefMut<T>, GetMutError> {
         if offset >= *self.len.borrow() {
             panic!("Index out of bounds")
         }
         let (row, col) = Self::index(offset);
         unsafe {
             let rows = &mut *self.rows.get();
-            Ok(ArenaRefMut(rows[row][col].try_borrow_mut()?))
+            let slot = rows[row][col]
+                .try_borrow_mut()
+                .map_err(GetMutError::Borrow)?;
+            if slot.gen != gen {
+                return Err(GetMutError::StaleIndex);
+            }
+            if slot.value.is_none() {
+                return Err(GetMutError::Removed);
+            }
+            Ok(ArenaRefMut(RefMut::map(slot, |slot| {
+                slot.value.as_mut().unwrap()
+            })))
+        }
+    }
+
+    fn try_remove(&self, offset: usize, gen: u32) -> Result<T, RemoveError> {
+        if offset >= *self.len.borrow() {
+            panic!("Index out of bounds")
+        }
+        let (row, col) = Self::index(offset);
+        let rows = unsafe { &mut *self.rows.get() };
+        let mut slot = rows[row][col]
+            .try_borrow_mut()
+            .map_err(RemoveError::Borrow)?;
+        if slot.gen != gen {
+            return Err(RemoveError::StaleIndex);
+        }
+        match slot.value.take() {
+            Some(t) => {
+                slot.gen = (slot.gen.wrapping_add(1) as u64
+                    & ArenaIdx::GEN_MASK) as u32;
+                drop(slot);
+                self.free.borrow_mut().push(offset);
+                Ok(t)
+            }
+            None => Err(RemoveError::Removed),
+        }
+    }
+
+    fn into_vec(mut self) -> Vec<T> {
+        let len = *self.len.borrow();
+        let rows = self.rows.get_mut();
+        let mut out = Vec::with_capacity(len);
+        for i in 0..len {
+            let (row, col) = Self::index(i);
+            if let Some(t) = rows[row][col].get_mut().value.take() {
+                out.push(t);
+            }
         }
+        out
+    }
+
+    fn drain(&self) -> Vec<T> {
+        let len = *self.len.borrow();
+        let rows = unsafe { &mut *self.rows.get() };
+        let mut out = Vec::with_capacity(len);
+        for i in 0..len {
+            let (row, col) = Self::index(i);
+            let mut slot = rows[row][col].borrow_mut();
+            if let Some(t) = slot.value.take() {
+                slot.gen = (slot.gen.wrapping_add(1) as u64
+                    & ArenaIdx::GEN_MASK) as u32;
+                drop(slot);
+                self.free.borrow_mut().push(i);
+                out.push(t);
+            }
+        }
+        out
     }
 
     // [0, 1]
@@ -256,6 +611,16 @@ impl<T> ArenaInner<T> {
     }
 
     pub fn append(&self, id: usize, t: T) -> ArenaIdx {
+        if let Some(offset) = self.free.borrow_mut().pop() {
+            let (row, col) = Self::index(offset);
+            let rows = unsafe { &mut *self.rows.get() };
+            let mut slot = rows[row][col].borrow_mut();
+            slot.value = Some(t);
+            let gen = slot.gen;
+            drop(slot);
+            return ArenaIdx::new(id, offset, gen);
+        }
+
         let i = *self.len.borrow();
         let (row, col) = Self::index(i);
         if row > 31 {
@@ -266,14 +631,119 @@ impl<T> ArenaInner<T> {
             // allocate new memory
             rows[row] = Vec::with_capacity(BASE << row);
         }
-        rows[row].push(RefCell::new(t));
+        rows[row].push(RefCell::new(Slot { gen: 0, value: Some(t) }));
         *self.len.borrow_mut() += 1;
 
-        ArenaIdx {
-            offset: i,
-            arena: id,
-            _marker: PhantomData,
+        ArenaIdx::new(id, i, 0)
+    }
+
+    fn append_iter<I: IntoIterator<Item = T>>(
+        &self,
+        id: usize,
+        iter: I,
+    ) -> Vec<ArenaIdx> {
+        let mut iter = iter.into_iter();
+        let mut idxs = Vec::new();
+
+        // Freed slots are scattered, so reuse them one at a time.
+        while let Some(offset) = self.free.borrow_mut().pop() {
+            let t = match iter.next() {
+                Some(t) => t,
+                None => {
+                    self.free.borrow_mut().push(offset);
+                    return idxs;
+                }
+            };
+            let (row, col) = Self::index(offset);
+            let rows = unsafe { &mut *self.rows.get() };
+            let mut slot = rows[row][col].borrow_mut();
+            slot.value = Some(t);
+            let gen = slot.gen;
+            drop(slot);
+            idxs.push(ArenaIdx::new(id, offset, gen));
+        }
+
+        let rest: Vec<T> = iter.collect();
+        if !rest.is_empty() {
+            idxs.extend(self.append_bulk(id, rest));
+        }
+        idxs
+    }
+
+    /// Appends `items` contiguously, reserving each target row's capacity
+    /// up front and spanning row boundaries as needed, instead of
+    /// bumping `len` and reallocating one element at a time.
+    fn append_bulk(&self, id: usize, items: Vec<T>) -> Vec<ArenaIdx> {
+        let mut idxs = Vec::with_capacity(items.len());
+        let mut items = items.into_iter();
+        let mut offset = *self.len.borrow();
+        let rows = unsafe { &mut *self.rows.get() };
+
+        while let Some(t) = items.next() {
+            let (row, col) = Self::index(offset);
+            if row > 31 {
+                panic!("Arena out of space!");
+            }
+            if col == 0 {
+                let row_len = BASE << row;
+                let mut new_row = Vec::with_capacity(row_len);
+                new_row.push(RefCell::new(Slot { gen: 0, value: Some(t) }));
+                idxs.push(ArenaIdx::new(id, offset, 0));
+                offset += 1;
+                while new_row.len() < row_len {
+                    match items.next() {
+                        Some(t) => {
+                            new_row.push(RefCell::new(Slot {
+                                gen: 0,
+                                value: Some(t),
+                            }));
+                            idxs.push(ArenaIdx::new(id, offset, 0));
+                            offset += 1;
+                        }
+                        None => break,
+                    }
+                }
+                rows[row] = new_row;
+            } else {
+                rows[row].push(RefCell::new(Slot { gen: 0, value: Some(t) }));
+                idxs.push(ArenaIdx::new(id, offset, 0));
+                offset += 1;
+            }
+        }
+
+        *self.len.borrow_mut() = offset;
+        idxs
+    }
+
+    fn live_offsets(&self) -> Vec<(usize, u32)> {
+        let len = *self.len.borrow();
+        let rows = unsafe { &*self.rows.get() };
+        let mut out = Vec::new();
+        for i in 0..len {
+            let (row, col) = Self::index(i);
+            let slot = rows[row][col].borrow();
+            if slot.value.is_some() {
+                out.push((i, slot.gen));
+            }
         }
+        out
+    }
+
+    fn cloned_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let len = *self.len.borrow();
+        let rows = unsafe { &*self.rows.get() };
+        let mut out = Vec::with_capacity(len);
+        for i in 0..len {
+            let (row, col) = Self::index(i);
+            let slot = rows[row][col].borrow();
+            if let Some(t) = &slot.value {
+                out.push(t.clone());
+            }
+        }
+        out
     }
 
     fn debug(&self, offset: usize, f: &mut fmt::Formatter) -> fmt::Result
@@ -287,7 +757,10 @@ impl<T> ArenaInner<T> {
         unsafe {
             let rows = self.rows.get();
             let inner = &*(*rows)[row][col].as_ptr();
-            write!(f, "{:?}", inner)
+            match &inner.value {
+                Some(t) => write!(f, "{:?}", t),
+                None => write!(f, "<removed>"),
+            }
         }
     }
 }
@@ -315,6 +788,15 @@ mod tests {
     use super::*;
     use std::rc::Rc;
 
+    #[test]
+    fn arena_idx_packs_offset_and_gen() {
+        assert_eq!(ArenaIdx::OFFSET_BITS + ArenaIdx::GEN_BITS, 64);
+
+        let idx = ArenaIdx::new(0, 12345, 7);
+        assert_eq!(idx.offset(), 12345);
+        assert_eq!(idx.gen(), 7);
+    }
+
     #[test]
     fn simple() {
         let arena = Arena::new();
@@ -440,4 +922,206 @@ mod tests {
         let string = format!("{:?}", arena);
         assert_eq!(&string, "{0: [0, 1]}")
     }
+
+    #[test]
+    fn remove() {
+        let arena = Arena::new();
+
+        let a = arena.append(0);
+        let b = arena.append(1);
+
+        assert_eq!(arena.remove(&a), Some(0));
+        assert!(arena.try_get(&a).is_err());
+        assert_eq!(*arena.get(&b), 1);
+
+        // removing again fails, the slot is already vacant
+        assert!(arena.remove(&a).is_none());
+
+        // appending now reuses the freed slot
+        let c = arena.append(2);
+        assert_eq!(*arena.get(&c), 2);
+    }
+
+    #[test]
+    fn stale_index_after_remove_and_reuse() {
+        let arena = Arena::new();
+
+        let a = arena.append(0);
+        arena.remove(&a).unwrap();
+
+        // reuses a's freed slot, whose generation was bumped on removal
+        let b = arena.append(1);
+
+        match arena.try_get(&a) {
+            Err(GetError::StaleIndex) => {}
+            other => panic!("expected StaleIndex, got {:?}", other),
+        }
+        assert_eq!(*arena.get(&b), 1);
+    }
+
+    #[test]
+    fn ref_map_projects_into_subfield() {
+        let arena = Arena::new();
+        let mut idx = arena.append((1, "one"));
+
+        let first = arena.get(&idx).map(|pair| &pair.0);
+        assert_eq!(*first, 1);
+        drop(first);
+
+        let mut second = arena.get_mut(&mut idx).map(|pair| &mut pair.1);
+        *second = "uno";
+        drop(second);
+
+        assert_eq!(*arena.get(&idx), (1, "uno"));
+    }
+
+    #[test]
+    fn into_vec_recovers_owned_values() {
+        let arena = Arena::new();
+
+        let a = arena.append(0);
+        arena.append(1);
+        arena.remove(&a);
+        arena.append(2);
+
+        assert_eq!(arena.into_vec(), vec![2, 1]);
+    }
+
+    #[test]
+    fn into_vec_clones_when_shared_with_a_clone() {
+        let arena_a = Arena::new();
+
+        // cloning hands a reference to arena_a's (then-empty) current
+        // storage to arena_b too, so it stays shared even though arena_b
+        // never reads through arena_a's id itself.
+        let _arena_b = arena_a.clone();
+
+        arena_a.append(0);
+        arena_a.append(1);
+
+        assert_eq!(arena_a.into_vec(), vec![0, 1]);
+    }
+
+    #[test]
+    fn drain_empties_the_arena_in_place() {
+        let arena = Arena::new();
+
+        let a = arena.append(0);
+        arena.append(1);
+
+        let drained: Vec<_> = arena.drain().collect();
+        assert_eq!(drained, vec![0, 1]);
+
+        assert!(arena.try_get(&a).is_err());
+
+        let b = arena.append(2);
+        assert_eq!(*arena.get(&b), 2);
+    }
+
+    #[test]
+    fn iter_skips_removed_entries() {
+        let arena = Arena::new();
+
+        let a = arena.append(0);
+        arena.append(1);
+        arena.append(2);
+        arena.remove(&a);
+
+        let values: Vec<_> =
+            arena.iter().map(|(_, v)| *v).collect();
+        assert_eq!(values, vec![1, 2]);
+
+        for (_, mut v) in arena.iter_mut() {
+            *v += 10;
+        }
+        let values: Vec<_> =
+            arena.iter().map(|(_, v)| *v).collect();
+        assert_eq!(values, vec![11, 12]);
+    }
+
+    #[test]
+    fn iter_skips_entries_removed_during_iteration() {
+        let arena = Arena::new();
+
+        arena.append(0);
+        arena.append(1);
+        let c = arena.append(2);
+
+        let mut seen = Vec::new();
+        for (i, (_, v)) in arena.iter_mut().enumerate() {
+            if i == 0 {
+                arena.remove(&c);
+            }
+            seen.push(*v);
+        }
+        assert_eq!(seen, vec![0, 1]);
+    }
+
+    #[test]
+    fn index_reads_the_value() {
+        let arena = Arena::new();
+        let a = arena.append(1);
+
+        assert_eq!(arena[a], 1);
+        // a second immutable index still succeeds: the leaked borrow
+        // from the first access is shared, not exclusive.
+        assert_eq!(arena[a], 1);
+    }
+
+    #[test]
+    fn index_mut_writes_the_value() {
+        let mut arena = Arena::new();
+        let a = arena.append(1);
+
+        arena[a] = 2;
+
+        // the leaked mutable borrow from the assignment above means a
+        // checked read through `get`/`Index` would now panic; inspect
+        // the raw value through `Debug` instead.
+        assert_eq!(format!("{:?}", arena), "{0: [2]}");
+    }
+
+    #[test]
+    fn append_slice_spans_a_row_boundary() {
+        let arena = Arena::new();
+
+        // the first row holds 32 elements (`BASE`), so this batch spans
+        // into the second row.
+        let values: Vec<i32> = (0..40).collect();
+        let idxs = arena.append_slice(&values);
+
+        assert_eq!(idxs.len(), 40);
+        for (i, idx) in idxs.iter().enumerate() {
+            assert_eq!(*arena.get(idx), i as i32);
+        }
+    }
+
+    #[test]
+    fn append_iter_reuses_freed_slots_before_growing() {
+        let arena = Arena::new();
+
+        let a = arena.append(0);
+        let b = arena.append(1);
+        arena.remove(&a);
+        arena.remove(&b);
+
+        let idxs = arena.append_iter(vec![10, 11, 12]);
+        assert_eq!(idxs.len(), 3);
+        // both freed offsets were refilled, so only one new slot was added
+        assert_eq!(format!("{:?}", arena), "{0: [11, 10, 12]}");
+
+        let mut values: Vec<_> =
+            idxs.iter().map(|idx| *arena.get(idx)).collect();
+        values.sort();
+        assert_eq!(values, vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn extend_appends_every_item() {
+        let mut arena = Arena::new();
+        arena.append(0);
+        arena.extend(vec![1, 2, 3]);
+
+        assert_eq!(arena.into_vec(), vec![0, 1, 2, 3]);
+    }
 }